@@ -0,0 +1,129 @@
+use anyhow::{anyhow, Result};
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use time::{macros::format_description, OffsetDateTime};
+
+/// A resolved author or committer: name, email, and the time the commit
+/// was made, formatted the way Git embeds it in a commit object.
+pub(crate) struct Identity {
+    name: String,
+    email: String,
+    timestamp: u64,
+    timezone: String,
+}
+
+impl Identity {
+    /// Resolves the author identity from `user.name`/`user.email` in
+    /// `.git/config`, falling back to `GIT_AUTHOR_NAME`/`GIT_AUTHOR_EMAIL`.
+    pub(crate) fn author() -> Result<Self> {
+        Self::resolve("GIT_AUTHOR_NAME", "GIT_AUTHOR_EMAIL")
+    }
+
+    /// Resolves the committer identity from `user.name`/`user.email` in
+    /// `.git/config`, falling back to `GIT_COMMITTER_NAME`/
+    /// `GIT_COMMITTER_EMAIL`.
+    pub(crate) fn committer() -> Result<Self> {
+        Self::resolve("GIT_COMMITTER_NAME", "GIT_COMMITTER_EMAIL")
+    }
+
+    fn resolve(name_var: &str, email_var: &str) -> Result<Self> {
+        let config = read_config().unwrap_or_default();
+
+        let name = pick(config.get("user.name").cloned(), env::var(name_var).ok()).ok_or_else(
+            || anyhow!("no identity found: set user.name in .git/config or {name_var}"),
+        )?;
+        let email = pick(config.get("user.email").cloned(), env::var(email_var).ok())
+            .ok_or_else(|| {
+                anyhow!("no identity found: set user.email in .git/config or {email_var}")
+            })?;
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let local = OffsetDateTime::now_local()?;
+        let timezone = local.format(&format_description!(
+            "[offset_hour sign:mandatory][offset_minute]"
+        ))?;
+
+        Ok(Self {
+            name,
+            email,
+            timestamp,
+            timezone,
+        })
+    }
+}
+
+/// Picks a config value over its environment-variable fallback.
+fn pick(config_value: Option<String>, env_value: Option<String>) -> Option<String> {
+    config_value.or(env_value)
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} <{}> {} {}",
+            self.name, self.email, self.timestamp, self.timezone
+        )
+    }
+}
+
+/// Parses the INI-style sections of `.git/config`, returning a flat map of
+/// `section.key` to value. Missing files resolve to an empty map.
+fn read_config() -> Result<HashMap<String, String>> {
+    let contents = match fs::read_to_string(".git/config") {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut section = String::new();
+    let mut values = HashMap::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            section = name.trim().to_string();
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim();
+            let value = value.trim().trim_matches('"');
+            values.insert(format!("{section}.{key}"), value.to_string());
+        }
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_value_takes_precedence_over_env() {
+        let picked = pick(
+            Some("Config Name".to_string()),
+            Some("Env Name".to_string()),
+        );
+        assert_eq!(picked.as_deref(), Some("Config Name"));
+    }
+
+    #[test]
+    fn falls_back_to_env_when_config_is_missing() {
+        let picked = pick(None, Some("Env Name".to_string()));
+        assert_eq!(picked.as_deref(), Some("Env Name"));
+    }
+
+    #[test]
+    fn is_none_when_neither_is_set() {
+        assert_eq!(pick(None, None), None);
+    }
+}
@@ -1,4 +1,8 @@
+mod identity;
 mod object;
+mod packet_line;
+mod packfile;
+mod transport;
 mod tree_entry;
 
 use anyhow::{bail, Result};
@@ -8,10 +12,11 @@ use std::path::{Path, PathBuf};
 use std::{env, io};
 use std::{
     fs,
-    io::{stdout, Write},
+    io::{stdin, stdout, BufRead, Read, Write},
 };
 
 use crate::object::{write_blob, write_commit, write_tree, Kind, Object, TreeIterator};
+use crate::packfile::{PackFile, PackFileEntry};
 
 #[derive(Parser)]
 struct Cli {
@@ -27,12 +32,19 @@ enum Command {
     LsTree(LsTreeArgs),
     WriteTree,
     CommitTree(CommitTreeArgs),
+    Clone(CloneArgs),
+    PackObjects,
 }
 
 #[derive(Args)]
+#[command(group(clap::ArgGroup::new("mode").required(true).args(["pretty_print", "show_type", "show_size"])))]
 struct CatFileArgs {
-    #[arg(short, required = true)]
+    #[arg(short = 'p')]
     pretty_print: bool,
+    #[arg(short = 't')]
+    show_type: bool,
+    #[arg(short = 's')]
+    show_size: bool,
     hash: String,
 }
 
@@ -59,6 +71,30 @@ struct CommitTreeArgs {
     parent: Option<String>,
 }
 
+#[derive(Args)]
+struct CloneArgs {
+    url: String,
+    dir: PathBuf,
+}
+
+/// Prints a tree object in `ls-tree`'s long format: mode, type, hash and
+/// name, one entry per line.
+fn print_tree(reader: impl BufRead, mut out: impl Write) -> Result<()> {
+    TreeIterator::new(reader).for_each(|entry| {
+        write!(
+            out,
+            "{:06o} {} {}\t",
+            entry.mode,
+            entry.object_type(),
+            hex::encode(entry.hash)
+        )?;
+        out.write_all(&entry.name)?;
+        writeln!(out)?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let args = Cli::parse();
 
@@ -73,12 +109,25 @@ fn main() -> Result<()> {
                 env::current_dir()?.display()
             );
         }
-        Command::CatFile(CatFileArgs { hash, .. }) => {
+        Command::CatFile(CatFileArgs {
+            hash,
+            show_type,
+            show_size,
+            ..
+        }) => {
             let mut object = Object::read(hash)?;
-            match object.kind {
-                Kind::Blob => io::copy(&mut object.reader, &mut stdout().lock())?,
-                _ => bail!("Not a blob"),
-            };
+            if *show_type {
+                println!("{}", object.kind);
+            } else if *show_size {
+                println!("{}", object.size);
+            } else {
+                match object.kind {
+                    Kind::Blob | Kind::Commit => {
+                        io::copy(&mut object.reader, &mut stdout().lock())?;
+                    }
+                    Kind::Tree => print_tree(object.reader, stdout().lock())?,
+                }
+            }
         }
         Command::HashObject(HashObjectArgs { path, .. }) => {
             let hash = write_blob(path)?;
@@ -87,25 +136,15 @@ fn main() -> Result<()> {
         Command::LsTree(LsTreeArgs { name_only, hash }) => {
             let object = Object::read(hash)?;
             match object.kind {
-                Kind::Tree => {
+                Kind::Tree if *name_only => {
                     let mut stdout = stdout().lock();
                     TreeIterator::new(object.reader).for_each(|entry| {
-                        if *name_only {
-                            stdout.write_all(&entry.name)?
-                        } else {
-                            write!(
-                                stdout,
-                                "{:06o} {} {}\t",
-                                entry.mode,
-                                entry.object_type(),
-                                hex::encode(entry.hash)
-                            )?;
-                            stdout.write_all(&entry.name)?;
-                        }
-                        writeln!(stdout, "")?;
+                        stdout.write_all(&entry.name)?;
+                        writeln!(stdout)?;
                         Ok(())
                     })?;
                 }
+                Kind::Tree => print_tree(object.reader, stdout().lock())?,
                 _ => bail!("Not a tree"),
             }
         }
@@ -121,6 +160,23 @@ fn main() -> Result<()> {
             let hash = write_commit(hash, message, parent.as_deref())?;
             println!("{}", hex::encode(hash));
         }
+        Command::Clone(CloneArgs { url, dir }) => {
+            transport::clone(url, dir)?;
+        }
+        Command::PackObjects => {
+            let mut input = String::new();
+            stdin().read_to_string(&mut input)?;
+
+            let mut pack = PackFile::new();
+            for hash in input.lines().map(str::trim).filter(|h| !h.is_empty()) {
+                let mut object = Object::read(hash)?;
+                let mut data = Vec::new();
+                object.reader.read_to_end(&mut data)?;
+                pack.add(PackFileEntry::new(object.kind, data));
+            }
+
+            pack.write(stdout().lock())?;
+        }
     }
     Ok(())
 }
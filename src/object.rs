@@ -10,14 +10,13 @@ use std::{
     fs::File,
     io::{self, BufRead, BufReader, Cursor, Read, Write},
     process,
-    time::{SystemTime, UNIX_EPOCH},
 };
 use std::{fs, path::Path};
-use time::{macros::format_description, OffsetDateTime};
 
+use crate::identity::Identity;
 use crate::tree_entry::TreeEntry;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Kind {
     Blob,
     Tree,
@@ -38,7 +37,7 @@ pub(crate) struct TreeIterator<R> {
 }
 
 #[derive(Debug)]
-struct HashWriter<W> {
+pub(crate) struct HashWriter<W> {
     writer: W,
     hasher: Sha1,
 }
@@ -88,21 +87,18 @@ impl Object<()> {
 }
 
 impl<R: Read> Object<R> {
-    fn write(mut self) -> Result<[u8; 20]> {
+    pub(crate) fn write(mut self) -> Result<[u8; 20]> {
         let tmp_path = format!(".git/objects/tmp-{}", process::id());
         let tmp_file = File::create(&tmp_path)?;
 
-        let mut writer = HashWriter {
-            writer: ZlibEncoder::new(tmp_file, Compression::default()),
-            hasher: Sha1::new(),
-        };
+        let mut writer = HashWriter::new(ZlibEncoder::new(tmp_file, Compression::default()));
 
         let header = format!("{} {}\0", self.kind, self.size);
         writer.write_all(header.as_bytes())?;
         io::copy(&mut self.reader, &mut writer)?;
 
-        writer.writer.finish()?;
-        let hash = writer.hasher.finalize();
+        let (hash, encoder) = writer.finalize();
+        encoder.finish()?;
         let hash_hex = hex::encode(hash);
 
         let dir_path = Path::new(".git/objects").join(&hash_hex[..2]);
@@ -110,7 +106,7 @@ impl<R: Read> Object<R> {
 
         fs::rename(tmp_path, dir_path.join(&hash_hex[2..]))?;
 
-        Ok(hash.into())
+        Ok(hash)
     }
 }
 
@@ -148,6 +144,19 @@ impl<R: BufRead> FallibleIterator for TreeIterator<R> {
     }
 }
 
+impl<W> HashWriter<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            writer,
+            hasher: Sha1::new(),
+        }
+    }
+
+    pub(crate) fn finalize(self) -> ([u8; 20], W) {
+        (self.hasher.finalize().into(), self.writer)
+    }
+}
+
 impl<W: Write> Write for HashWriter<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let n = self.writer.write(buf)?;
@@ -228,21 +237,11 @@ pub(crate) fn write_commit(hash: &str, message: &str, parent: Option<&str>) -> R
         writeln!(buf, "parent {parent}")?;
     }
 
-    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-
-    let local = OffsetDateTime::now_local()?;
-    let timezone = local.format(&format_description!(
-        "[offset_hour sign:mandatory][offset_minute]"
-    ))?;
-
-    writeln!(
-        buf,
-        "author toxeeec <bartosz.kapciak@gmail.com> {timestamp} {timezone}",
-    )?;
-    writeln!(
-        buf,
-        "commiter toxeeec <bartosz.kapciak@gmail.com> {timestamp} {timezone}",
-    )?;
+    let author = Identity::author()?;
+    let committer = Identity::committer()?;
+
+    writeln!(buf, "author {author}")?;
+    writeln!(buf, "committer {committer}")?;
     writeln!(buf, "")?;
     writeln!(buf, "{message}")?;
 
@@ -0,0 +1,161 @@
+use anyhow::{anyhow, bail, Context, Result};
+use fallible_iterator::FallibleIterator;
+use std::io::Write;
+
+/// A single pkt-line, decoded from its 4-hex-digit length prefix.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Packet<'a> {
+    Flush,
+    Delimiter,
+    ResponseEnd,
+    Data(&'a [u8]),
+}
+
+/// Decodes a byte slice as a stream of pkt-lines.
+#[derive(Debug)]
+pub(crate) struct PacketLineDecoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> PacketLineDecoder<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> FallibleIterator for PacketLineDecoder<'a> {
+    type Item = Packet<'a>;
+    type Error = anyhow::Error;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+        if self.data.len() < 4 {
+            bail!("truncated pkt-line");
+        }
+
+        let len = usize::from_str_radix(std::str::from_utf8(&self.data[..4])?, 16)
+            .context("invalid pkt-line length")?;
+
+        let packet = match len {
+            0 => Packet::Flush,
+            1 => Packet::Delimiter,
+            2 => Packet::ResponseEnd,
+            len if len < 4 => bail!("invalid pkt-line length: {len}"),
+            len => {
+                if self.data.len() < len {
+                    bail!("truncated pkt-line body");
+                }
+                Packet::Data(&self.data[4..len])
+            }
+        };
+
+        self.data = &self.data[len.max(4)..];
+        Ok(Some(packet))
+    }
+}
+
+/// Appends `payload` as a pkt-line, prefixed with its hex length.
+pub(crate) fn encode(out: &mut Vec<u8>, payload: &[u8]) {
+    write!(out, "{:04x}", payload.len() + 4).unwrap();
+    out.extend_from_slice(payload);
+}
+
+/// Appends a flush packet (`0000`).
+pub(crate) fn flush(out: &mut Vec<u8>) {
+    out.extend_from_slice(b"0000");
+}
+
+/// A demultiplexed sideband-64k packet.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Band<'a> {
+    Pack(&'a [u8]),
+    Progress(&'a [u8]),
+    Error(&'a [u8]),
+}
+
+/// Splits a sideband-64k payload into its channel and data, per the first
+/// byte: 1 = pack data, 2 = progress message, 3 = error message.
+pub(crate) fn demux_sideband(payload: &[u8]) -> Result<Band<'_>> {
+    let (channel, rest) = payload
+        .split_first()
+        .ok_or_else(|| anyhow!("empty sideband packet"))?;
+
+    match channel {
+        1 => Ok(Band::Pack(rest)),
+        2 => Ok(Band::Progress(rest)),
+        3 => Ok(Band::Error(rest)),
+        other => bail!("unknown sideband channel: {other}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_and_decodes_a_data_packet() {
+        let mut buf = Vec::new();
+        encode(&mut buf, b"hello\n");
+
+        let mut decoder = PacketLineDecoder::new(&buf);
+        assert_eq!(decoder.next().unwrap(), Some(Packet::Data(b"hello\n")));
+        assert_eq!(decoder.next().unwrap(), None);
+    }
+
+    #[test]
+    fn decodes_a_flush_packet() {
+        let mut buf = Vec::new();
+        flush(&mut buf);
+
+        let mut decoder = PacketLineDecoder::new(&buf);
+        assert_eq!(decoder.next().unwrap(), Some(Packet::Flush));
+        assert_eq!(decoder.next().unwrap(), None);
+    }
+
+    #[test]
+    fn decodes_delimiter_and_response_end_packets() {
+        let buf = b"00010002".to_vec();
+        let mut decoder = PacketLineDecoder::new(&buf);
+        assert_eq!(decoder.next().unwrap(), Some(Packet::Delimiter));
+        assert_eq!(decoder.next().unwrap(), Some(Packet::ResponseEnd));
+        assert_eq!(decoder.next().unwrap(), None);
+    }
+
+    #[test]
+    fn decodes_a_sequence_of_packets() {
+        let mut buf = Vec::new();
+        encode(&mut buf, b"first");
+        encode(&mut buf, b"second");
+        flush(&mut buf);
+
+        let mut decoder = PacketLineDecoder::new(&buf);
+        assert_eq!(decoder.next().unwrap(), Some(Packet::Data(b"first")));
+        assert_eq!(decoder.next().unwrap(), Some(Packet::Data(b"second")));
+        assert_eq!(decoder.next().unwrap(), Some(Packet::Flush));
+        assert_eq!(decoder.next().unwrap(), None);
+    }
+
+    #[test]
+    fn truncated_pkt_line_errors() {
+        let buf = b"0010abc".to_vec(); // claims 16 bytes, only has 7
+        let mut decoder = PacketLineDecoder::new(&buf);
+        assert!(decoder.next().is_err());
+    }
+
+    #[test]
+    fn length_below_header_size_errors_instead_of_panicking() {
+        let buf = b"0003xxxxxxxx".to_vec(); // length header of 3 is shorter than the header itself
+        let mut decoder = PacketLineDecoder::new(&buf);
+        assert!(decoder.next().is_err());
+    }
+
+    #[test]
+    fn demuxes_sideband_channels() {
+        assert_eq!(demux_sideband(&[1, b'a', b'b']).unwrap(), Band::Pack(b"ab"));
+        assert_eq!(demux_sideband(&[2, b'p']).unwrap(), Band::Progress(b"p"));
+        assert_eq!(demux_sideband(&[3, b'e']).unwrap(), Band::Error(b"e"));
+        assert!(demux_sideband(&[4, b'x']).is_err());
+    }
+}
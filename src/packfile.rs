@@ -0,0 +1,428 @@
+use anyhow::{bail, Result};
+use flate2::read::ZlibDecoder;
+use flate2::{write::ZlibEncoder, Compression};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+
+use crate::object::{HashWriter, Kind, Object};
+
+const SIGNATURE: &[u8; 4] = b"PACK";
+
+/// A single object bound for a `.pack` file.
+pub(crate) struct PackFileEntry {
+    pub(crate) kind: Kind,
+    pub(crate) data: Vec<u8>,
+}
+
+impl PackFileEntry {
+    pub(crate) fn new(kind: Kind, data: Vec<u8>) -> Self {
+        Self { kind, data }
+    }
+}
+
+/// Builds a `.pack` byte stream from a set of objects, mirroring `unpack`.
+#[derive(Default)]
+pub(crate) struct PackFile {
+    entries: Vec<PackFileEntry>,
+}
+
+impl PackFile {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn add(&mut self, entry: PackFileEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Writes the `PACK` magic, version 2, the entry count, each object's
+    /// variable-length type+size header followed by its zlib-compressed
+    /// body, then a trailing SHA-1 over everything written so far.
+    pub(crate) fn write<W: Write>(&self, writer: W) -> Result<()> {
+        let mut writer = HashWriter::new(writer);
+
+        writer.write_all(SIGNATURE)?;
+        writer.write_all(&2u32.to_be_bytes())?;
+        writer.write_all(&(self.entries.len() as u32).to_be_bytes())?;
+
+        for entry in &self.entries {
+            write_object_header(&mut writer, entry.kind, entry.data.len())?;
+
+            let mut encoder = ZlibEncoder::new(&mut writer, Compression::default());
+            encoder.write_all(&entry.data)?;
+            encoder.finish()?;
+        }
+
+        let (hash, mut writer) = writer.finalize();
+        writer.write_all(&hash)?;
+
+        Ok(())
+    }
+}
+
+fn write_object_header<W: Write>(writer: &mut W, kind: Kind, size: usize) -> Result<()> {
+    let kind_bits = match kind {
+        Kind::Commit => 1,
+        Kind::Tree => 2,
+        Kind::Blob => 3,
+    };
+
+    let mut size = size;
+    let mut byte = (kind_bits << 4) | (size & 0b1111) as u8;
+    size >>= 4;
+    if size > 0 {
+        byte |= 0b1000_0000;
+    }
+    writer.write_all(&[byte])?;
+
+    while size > 0 {
+        let mut byte = (size & 0b0111_1111) as u8;
+        size >>= 7;
+        if size > 0 {
+            byte |= 0b1000_0000;
+        }
+        writer.write_all(&[byte])?;
+    }
+
+    Ok(())
+}
+
+enum RawKind {
+    Commit,
+    Tree,
+    Blob,
+    OfsDelta,
+    RefDelta,
+}
+
+struct RawEntry {
+    offset: u64,
+    kind: RawKind,
+    body: Vec<u8>,
+    base_offset: Option<u64>,
+    base_hash: Option<[u8; 20]>,
+}
+
+/// Decodes a `.pack` byte stream, resolving OFS/REF deltas against their
+/// bases, and writes every object it contains into `.git/objects`.
+///
+/// Returns the hash of each object, in the order it was resolved. A
+/// ref-delta may reference a base that hasn't been seen yet in the stream,
+/// so undelta-able entries are buffered and retried once their base has been
+/// resolved from later in this pack; a base already on disk is used directly.
+pub(crate) fn unpack(data: &[u8]) -> Result<Vec<[u8; 20]>> {
+    let mut pending = read_entries(data)?;
+
+    let mut by_offset: HashMap<u64, (Kind, Vec<u8>)> = HashMap::new();
+    let mut by_hash: HashMap<[u8; 20], (Kind, Vec<u8>)> = HashMap::new();
+    let mut hashes = Vec::with_capacity(pending.len());
+
+    while !pending.is_empty() {
+        let mut unresolved = Vec::new();
+        let mut progressed = false;
+
+        for entry in pending {
+            match resolve_entry(&entry, &by_offset, &by_hash)? {
+                Some((kind, body)) => {
+                    progressed = true;
+                    let object = Object {
+                        kind,
+                        size: body.len() as u64,
+                        reader: Cursor::new(body.clone()),
+                    };
+                    let hash = object.write()?;
+                    by_offset.insert(entry.offset, (kind, body.clone()));
+                    by_hash.insert(hash, (kind, body));
+                    hashes.push(hash);
+                }
+                None => unresolved.push(entry),
+            }
+        }
+
+        if !progressed {
+            bail!(
+                "failed to resolve {} delta object(s): missing base",
+                unresolved.len()
+            );
+        }
+        pending = unresolved;
+    }
+
+    Ok(hashes)
+}
+
+fn read_entries(data: &[u8]) -> Result<Vec<RawEntry>> {
+    let mut cursor = Cursor::new(data);
+
+    let mut signature = [0; 4];
+    cursor.read_exact(&mut signature)?;
+    if &signature != SIGNATURE {
+        bail!("Invalid packfile signature");
+    }
+
+    let version = read_u32(&mut cursor)?;
+    if version != 2 {
+        bail!("Unsupported packfile version: {version}");
+    }
+
+    let count = read_u32(&mut cursor)?;
+    let mut entries = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        let offset = cursor.position();
+        let (kind_byte, size) = read_object_header(&mut cursor)?;
+
+        let (kind, base_offset, base_hash) = match kind_byte {
+            1 => (RawKind::Commit, None, None),
+            2 => (RawKind::Tree, None, None),
+            3 => (RawKind::Blob, None, None),
+            4 => bail!("tag objects are not supported yet"),
+            6 => {
+                let distance = read_offset_delta(&mut cursor)?;
+                (RawKind::OfsDelta, Some(offset - distance), None)
+            }
+            7 => {
+                let mut hash = [0; 20];
+                cursor.read_exact(&mut hash)?;
+                (RawKind::RefDelta, None, Some(hash))
+            }
+            other => bail!("Unknown object type: {other}"),
+        };
+
+        let start = cursor.position() as usize;
+        let mut decoder = ZlibDecoder::new(&data[start..]);
+        let mut body = Vec::with_capacity(size);
+        decoder.read_to_end(&mut body)?;
+        cursor.set_position((start as u64) + decoder.total_in());
+
+        entries.push(RawEntry {
+            offset,
+            kind,
+            body,
+            base_offset,
+            base_hash,
+        });
+    }
+
+    Ok(entries)
+}
+
+fn resolve_entry(
+    entry: &RawEntry,
+    by_offset: &HashMap<u64, (Kind, Vec<u8>)>,
+    by_hash: &HashMap<[u8; 20], (Kind, Vec<u8>)>,
+) -> Result<Option<(Kind, Vec<u8>)>> {
+    match &entry.kind {
+        RawKind::Commit => Ok(Some((Kind::Commit, entry.body.clone()))),
+        RawKind::Tree => Ok(Some((Kind::Tree, entry.body.clone()))),
+        RawKind::Blob => Ok(Some((Kind::Blob, entry.body.clone()))),
+        RawKind::OfsDelta => {
+            let base_offset = entry.base_offset.expect("ofs-delta always has a base offset");
+            match by_offset.get(&base_offset) {
+                Some((kind, base)) => Ok(Some((*kind, apply_delta(base, &entry.body)?))),
+                None => Ok(None),
+            }
+        }
+        RawKind::RefDelta => {
+            let base_hash = entry.base_hash.expect("ref-delta always has a base hash");
+            if let Some((kind, base)) = by_hash.get(&base_hash) {
+                return Ok(Some((*kind, apply_delta(base, &entry.body)?)));
+            }
+
+            match Object::read(&hex::encode(base_hash)) {
+                Ok(mut base_object) => {
+                    let mut base = Vec::new();
+                    base_object.reader.read_to_end(&mut base)?;
+                    Ok(Some((base_object.kind, apply_delta(&base, &entry.body)?)))
+                }
+                Err(_) => Ok(None),
+            }
+        }
+    }
+}
+
+fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    let mut pos = 0;
+    let base_size = read_delta_size(delta, &mut pos)?;
+    if base_size as usize != base.len() {
+        bail!("delta base size mismatch");
+    }
+    let result_size = read_delta_size(delta, &mut pos)?;
+
+    let mut result = Vec::with_capacity(result_size as usize);
+    while pos < delta.len() {
+        let opcode = next_byte(delta, &mut pos)?;
+
+        if opcode & 0x80 != 0 {
+            let mut offset: u32 = 0;
+            let mut size: u32 = 0;
+            for i in 0..4 {
+                if opcode & (1 << i) != 0 {
+                    offset |= (next_byte(delta, &mut pos)? as u32) << (i * 8);
+                }
+            }
+            for i in 0..3 {
+                if opcode & (1 << (4 + i)) != 0 {
+                    size |= (next_byte(delta, &mut pos)? as u32) << (i * 8);
+                }
+            }
+            let size = if size == 0 { 0x10000 } else { size } as usize;
+            let offset = offset as usize;
+            result.extend_from_slice(
+                base.get(offset..offset + size)
+                    .ok_or_else(|| anyhow::anyhow!("delta copy instruction out of bounds"))?,
+            );
+        } else if opcode != 0 {
+            let size = opcode as usize;
+            let end = pos + size;
+            result.extend_from_slice(
+                delta
+                    .get(pos..end)
+                    .ok_or_else(|| anyhow::anyhow!("delta insert instruction out of bounds"))?,
+            );
+            pos = end;
+        } else {
+            bail!("invalid delta opcode 0");
+        }
+    }
+
+    if result.len() != result_size as usize {
+        bail!("delta result size mismatch");
+    }
+
+    Ok(result)
+}
+
+fn next_byte(delta: &[u8], pos: &mut usize) -> Result<u8> {
+    let byte = *delta
+        .get(*pos)
+        .ok_or_else(|| anyhow::anyhow!("truncated delta"))?;
+    *pos += 1;
+    Ok(byte)
+}
+
+fn read_delta_size(delta: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut result = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *delta
+            .get(*pos)
+            .ok_or_else(|| anyhow::anyhow!("truncated delta"))?;
+        *pos += 1;
+        result |= ((byte & 0x7f) as u64) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+fn read_object_header(cursor: &mut Cursor<&[u8]>) -> Result<(u8, usize)> {
+    let mut byte = read_u8(cursor)?;
+    let kind = (byte >> 4) & 0b111;
+    let mut size = (byte & 0b1111) as usize;
+    let mut shift = 4;
+
+    while byte & 0b1000_0000 != 0 {
+        byte = read_u8(cursor)?;
+        size |= ((byte & 0b0111_1111) as usize) << shift;
+        shift += 7;
+    }
+
+    Ok((kind, size))
+}
+
+fn read_offset_delta(cursor: &mut Cursor<&[u8]>) -> Result<u64> {
+    let mut byte = read_u8(cursor)?;
+    let mut offset = (byte & 0x7f) as u64;
+    while byte & 0x80 != 0 {
+        byte = read_u8(cursor)?;
+        offset += 1;
+        offset = (offset << 7) | (byte & 0x7f) as u64;
+    }
+    Ok(offset)
+}
+
+fn read_u32(cursor: &mut Cursor<&[u8]>) -> Result<u32> {
+    let mut buf = [0; 4];
+    cursor.read_exact(&mut buf)?;
+    Ok(u32::from_be_bytes(buf))
+}
+
+fn read_u8(cursor: &mut Cursor<&[u8]>) -> Result<u8> {
+    let mut buf = [0; 1];
+    cursor.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_delta_size(mut n: usize, out: &mut Vec<u8>) {
+        loop {
+            let mut byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if n > 0 {
+                byte |= 0x80;
+            }
+            out.push(byte);
+            if n == 0 {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn applies_copy_and_insert_instructions() {
+        let base = b"hello world";
+        let mut delta = Vec::new();
+        encode_delta_size(base.len(), &mut delta);
+        encode_delta_size(b"world hello".len(), &mut delta);
+        delta.push(0b1001_0001); // copy: 1 offset byte, 1 size byte
+        delta.push(6); // offset
+        delta.push(5); // size
+        delta.push(6); // insert 6 literal bytes
+        delta.extend_from_slice(b" hello");
+
+        let result = apply_delta(base, &delta).unwrap();
+        assert_eq!(result, b"world hello");
+    }
+
+    #[test]
+    fn truncated_insert_instruction_errors_instead_of_panicking() {
+        let base = b"hello";
+        let mut delta = Vec::new();
+        encode_delta_size(base.len(), &mut delta);
+        encode_delta_size(127, &mut delta);
+        delta.push(127); // insert opcode claims 127 bytes
+        delta.extend_from_slice(b"ab"); // but only 2 are present
+
+        assert!(apply_delta(base, &delta).is_err());
+    }
+
+    #[test]
+    fn truncated_copy_instruction_errors_instead_of_panicking() {
+        let base = b"hello";
+        let mut delta = Vec::new();
+        encode_delta_size(base.len(), &mut delta);
+        encode_delta_size(5, &mut delta);
+        delta.push(0b1000_0001); // copy opcode expecting an offset byte that never comes
+
+        assert!(apply_delta(base, &delta).is_err());
+    }
+
+    #[test]
+    fn copy_instruction_past_base_end_errors() {
+        let base = b"hello";
+        let mut delta = Vec::new();
+        encode_delta_size(base.len(), &mut delta);
+        encode_delta_size(5, &mut delta);
+        delta.push(0b1001_0001); // copy: 1 offset byte, 1 size byte
+        delta.push(0); // offset
+        delta.push(255); // size, far past the base's length
+
+        assert!(apply_delta(base, &delta).is_err());
+    }
+}
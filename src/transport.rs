@@ -0,0 +1,187 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::ffi::OsStr;
+use std::io::Read;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::{env, fs, path::Path};
+
+use crate::object::{Kind, Object, TreeIterator};
+use crate::packet_line::{self, Band, Packet, PacketLineDecoder};
+use crate::packfile;
+use crate::tree_entry::TreeEntryMode;
+use fallible_iterator::FallibleIterator;
+
+struct RemoteRef {
+    hash: String,
+    name: String,
+}
+
+/// Clones `url` into `dir` over the Git smart-HTTP (v1) protocol: discovers
+/// refs, fetches a packfile for them, then checks out HEAD into the tree.
+pub(crate) fn clone(url: &str, dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    env::set_current_dir(dir)?;
+
+    fs::create_dir(".git")?;
+    fs::create_dir(".git/objects")?;
+    fs::create_dir(".git/refs")?;
+
+    let refs = discover_refs(url)?;
+    let head = refs
+        .iter()
+        .find(|r| r.name == "HEAD")
+        .ok_or_else(|| anyhow!("remote repository has no HEAD"))?;
+    let head_hash = head.hash.clone();
+
+    let branch = refs
+        .iter()
+        .find(|r| r.name != "HEAD" && r.hash == head_hash)
+        .map(|r| r.name.clone())
+        .unwrap_or_else(|| "refs/heads/main".to_string());
+
+    let pack = fetch_pack(url, &refs)?;
+    packfile::unpack(&pack)?;
+
+    fs::write(".git/HEAD", format!("ref: {branch}\n"))?;
+    let ref_path = Path::new(".git").join(&branch);
+    fs::create_dir_all(ref_path.parent().unwrap())?;
+    fs::write(ref_path, format!("{head_hash}\n"))?;
+
+    checkout(&head_hash, Path::new("."))
+}
+
+fn discover_refs(url: &str) -> Result<Vec<RemoteRef>> {
+    let resp = ureq::get(&format!("{url}/info/refs"))
+        .query("service", "git-upload-pack")
+        .call()
+        .context("failed to discover remote refs")?;
+
+    let mut body = Vec::new();
+    resp.into_reader().read_to_end(&mut body)?;
+
+    let mut decoder = PacketLineDecoder::new(&body);
+    // "# service=git-upload-pack\n" announcement, then a flush packet.
+    decoder.next()?;
+    decoder.next()?;
+
+    let mut refs = Vec::new();
+    while let Some(packet) = decoder.next()? {
+        let Packet::Data(line) = packet else { break };
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        // The first ref line carries capabilities after a NUL; ignore them.
+        let line = line.split(|&b| b == 0).next().unwrap();
+        let space = line
+            .iter()
+            .position(|&b| b == b' ')
+            .ok_or_else(|| anyhow!("malformed ref advertisement"))?;
+        let (hash, name) = (&line[..space], &line[space + 1..]);
+
+        refs.push(RemoteRef {
+            hash: std::str::from_utf8(hash)?.to_string(),
+            name: std::str::from_utf8(name)?.to_string(),
+        });
+    }
+
+    Ok(refs)
+}
+
+fn fetch_pack(url: &str, refs: &[RemoteRef]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut wanted = refs.iter().filter(|r| r.name != "HEAD");
+    if let Some(first) = wanted.next() {
+        packet_line::encode(
+            &mut body,
+            format!("want {} side-band-64k\n", first.hash).as_bytes(),
+        );
+    }
+    for r in wanted {
+        packet_line::encode(&mut body, format!("want {}\n", r.hash).as_bytes());
+    }
+    packet_line::flush(&mut body);
+    packet_line::encode(&mut body, b"done\n");
+
+    let resp = ureq::post(&format!("{url}/git-upload-pack"))
+        .set("Content-Type", "application/x-git-upload-pack-request")
+        .send_bytes(&body)
+        .context("failed to fetch packfile")?;
+
+    let mut raw = Vec::new();
+    resp.into_reader().read_to_end(&mut raw)?;
+
+    demux_pack(&raw)
+}
+
+fn demux_pack(data: &[u8]) -> Result<Vec<u8>> {
+    let mut pack = Vec::new();
+    let mut decoder = PacketLineDecoder::new(data);
+    while let Some(packet) = decoder.next()? {
+        let Packet::Data(line) = packet else { continue };
+        if line.starts_with(b"NAK") || line.starts_with(b"ACK") {
+            continue;
+        }
+        match packet_line::demux_sideband(line)? {
+            Band::Pack(bytes) => pack.extend_from_slice(bytes),
+            Band::Progress(_) | Band::Error(_) => {}
+        }
+    }
+    Ok(pack)
+}
+
+fn checkout(commit_hash: &str, root: &Path) -> Result<()> {
+    let commit = Object::read(commit_hash)?;
+    if commit.kind != Kind::Commit {
+        bail!("{commit_hash} is not a commit");
+    }
+
+    let mut reader = commit.reader;
+    let mut body = String::new();
+    reader.read_to_string(&mut body)?;
+
+    let tree_hash = body
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("tree "))
+        .ok_or_else(|| anyhow!("malformed commit object"))?;
+
+    checkout_tree(tree_hash, root)
+}
+
+fn checkout_tree(tree_hash: &str, dir: &Path) -> Result<()> {
+    let tree = Object::read(tree_hash)?;
+    if tree.kind != Kind::Tree {
+        bail!("{tree_hash} is not a tree");
+    }
+
+    TreeIterator::new(tree.reader).for_each(|entry| {
+        let path = dir.join(OsStr::from_bytes(&entry.name));
+        let hash = hex::encode(entry.hash);
+
+        match entry.mode {
+            TreeEntryMode::Directory => {
+                fs::create_dir_all(&path)?;
+                checkout_tree(&hash, &path)?;
+            }
+            TreeEntryMode::Symlink => {
+                let mut object = Object::read(&hash)?;
+                let mut target = Vec::new();
+                object.reader.read_to_end(&mut target)?;
+                symlink(OsStr::from_bytes(&target), &path)?;
+            }
+            _ => {
+                let mut object = Object::read(&hash)?;
+                let mut content = Vec::new();
+                object.reader.read_to_end(&mut content)?;
+                fs::write(&path, &content)?;
+                if entry.mode == TreeEntryMode::ExecutableFile {
+                    let mut perms = fs::metadata(&path)?.permissions();
+                    perms.set_mode(0o755);
+                    fs::set_permissions(&path, perms)?;
+                }
+            }
+        }
+
+        Ok(())
+    })?;
+
+    Ok(())
+}